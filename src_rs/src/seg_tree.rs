@@ -1,6 +1,7 @@
 use std::{
     iter::FromIterator,
-    ops::{Bound, Range, RangeBounds},
+    ops::{Bound, Deref, DerefMut, Range, RangeBounds},
+    rc::Rc,
 };
 
 /// # Constraint
@@ -84,7 +85,7 @@ impl<T: SegTreeType> SegTree<T> {
                 if i < mid {
                     left.get(i)
                 } else {
-                    right.get(i)
+                    right.get(i - mid)
                 }
             }
         }
@@ -237,6 +238,66 @@ impl<T: SegTreeType> SegTree<T> {
         assert!(end <= self.len(), "index out: {}/{}", end, self.len());
         Range { start, end }
     }
+    fn get_mut(&mut self, i: usize) -> &mut T::Item {
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if i < mid {
+                    left.get_mut(i)
+                } else {
+                    right.get_mut(i - mid)
+                }
+            }
+        }
+    }
+    /// `i` 番目の葉から根までの `prod` を、葉の値を書き換えずに再計算する
+    fn fix_up(&mut self, i: usize) {
+        if let Self::Node {
+            left, right, prod, ..
+        } = self
+        {
+            let mid = left.len();
+            if i < mid {
+                left.fix_up(i)
+            } else {
+                right.fix_up(i - mid)
+            }
+            *prod = T::prod(left.prod_ref(), right.prod_ref());
+        }
+    }
+    /// `i` 番目への書き込み用ガード
+    ///
+    /// `Entry` を経由して値を書き換えると、drop 時に根までの `prod` が再計算される
+    pub fn entry(&mut self, i: usize) -> Entry<'_, T> {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        Entry { tree: self, i }
+    }
+}
+
+/// [`SegTree::entry`] が返す書き込み用ガード
+pub struct Entry<'a, T: SegTreeType> {
+    tree: &'a mut SegTree<T>,
+    i: usize,
+}
+
+impl<'a, T: SegTreeType> Deref for Entry<'a, T> {
+    type Target = T::Item;
+    fn deref(&self) -> &T::Item {
+        self.tree.get(self.i)
+    }
+}
+
+impl<'a, T: SegTreeType> DerefMut for Entry<'a, T> {
+    fn deref_mut(&mut self) -> &mut T::Item {
+        self.tree.get_mut(self.i)
+    }
+}
+
+impl<'a, T: SegTreeType> Drop for Entry<'a, T> {
+    fn drop(&mut self) {
+        self.tree.fix_up(self.i);
+    }
 }
 
 impl<T: SegTreeType> From<&[T::Item]> for SegTree<T> {
@@ -250,3 +311,1224 @@ impl<T: SegTreeType> FromIterator<T::Item> for SegTree<T> {
         Self::from(&iter.into_iter().collect::<Vec<_>>()[..])
     }
 }
+
+/// # Constraint
+/// - `prod(id(), a) = prod(a, id()) = a`
+/// - `prod(a, prod(b, c)) = prod(prod(a, b), c)`
+/// - `compose(act_id(), f) = compose(f, act_id()) = f`
+/// - `compose(newer, compose(older, f)) = compose(compose(newer, older), f)`
+/// - `map(f, id(), len) = id()`
+/// - `map(compose(newer, older), x, len) = map(newer, map(older, x, len), len)`
+pub trait LazySegTreeType: SegTreeType {
+    type Act: Clone;
+    fn act_id() -> Self::Act;
+    /// `older` を適用した後に `newer` を適用した作用
+    fn compose(newer: &Self::Act, older: &Self::Act) -> Self::Act;
+    /// `len` 個の要素の積である `x` に `f` を適用する
+    fn map(f: &Self::Act, x: &Self::Item, len: usize) -> Self::Item;
+}
+
+pub enum LazySegTree<T: LazySegTreeType> {
+    Leaf {
+        val: T::Item,
+    },
+    Node {
+        len: usize,
+        prod: T::Item,
+        lazy: T::Act,
+        left: Box<Self>,
+        right: Box<Self>,
+    },
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl<T: LazySegTreeType> LazySegTree<T> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Node { len, .. } => *len,
+        }
+    }
+    pub fn prod_ref(&self) -> &T::Item {
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { prod, .. } => prod,
+        }
+    }
+    pub fn prod(&self) -> T::Item {
+        self.prod_ref().clone()
+    }
+    /// `T::id()` が `n` 個
+    pub fn new(n: usize) -> Self {
+        assert_ne!(n, 0, "empty segtree does not exsist");
+        if n == 1 {
+            Self::Leaf { val: T::id() }
+        } else {
+            Self::Node {
+                len: n,
+                prod: T::id(),
+                lazy: T::act_id(),
+                left: Box::new(Self::new(n / 2)),
+                right: Box::new(Self::new(n - n / 2)),
+            }
+        }
+    }
+    /// スライスから生成
+    fn from_slice(slice: &[T::Item]) -> Self {
+        assert!(!slice.is_empty(), "empty segtree does not exist");
+        if slice.len() == 1 {
+            Self::Leaf {
+                val: slice[0].clone(),
+            }
+        } else {
+            let mid = slice.len() / 2;
+            let left = Self::from_slice(&slice[..mid]);
+            let right = Self::from_slice(&slice[mid..]);
+            Self::Node {
+                len: slice.len(),
+                prod: T::prod(left.prod_ref(), right.prod_ref()),
+                lazy: T::act_id(),
+                left: Box::new(left),
+                right: Box::new(right),
+            }
+        }
+    }
+    /// 自分自身の部分木全体に `f` を適用する
+    fn apply_all(&mut self, f: &T::Act) {
+        match self {
+            Self::Leaf { val } => *val = T::map(f, val, 1),
+            Self::Node { len, prod, lazy, .. } => {
+                *prod = T::map(f, prod, *len);
+                *lazy = T::compose(f, lazy);
+            }
+        }
+    }
+    /// 自分の持つ遅延作用を両方の子に伝播し、自分の遅延作用を単位元に戻す
+    fn push_down(&mut self) {
+        if let Self::Node {
+            lazy, left, right, ..
+        } = self
+        {
+            left.apply_all(lazy);
+            right.apply_all(lazy);
+            *lazy = T::act_id();
+        }
+    }
+    /// `i` 番目を得る O(log n)
+    pub fn get(&mut self, i: usize) -> &T::Item {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        if let Self::Leaf { val } = self {
+            return val;
+        }
+        self.push_down();
+        if let Self::Node { left, right, .. } = self {
+            let mid = left.len();
+            if i < mid {
+                left.get(i)
+            } else {
+                right.get(i - mid)
+            }
+        } else {
+            unreachable!()
+        }
+    }
+    /// `i` 番目を `v` にする O(log n)
+    pub fn set(&mut self, i: usize, v: T::Item) {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        if let Self::Leaf { val } = self {
+            *val = v;
+            return;
+        }
+        self.push_down();
+        if let Self::Node {
+            left, right, prod, ..
+        } = self
+        {
+            let mid = left.len();
+            if i < mid {
+                left.set(i, v)
+            } else {
+                right.set(i - mid, v)
+            }
+            *prod = T::prod(left.prod_ref(), right.prod_ref());
+        }
+    }
+    /// 添字範囲 `range` の要素の積 O(log n)
+    pub fn prod_range(&mut self, range: impl RangeBounds<usize>) -> T::Item {
+        let Range { start, end } = self.range_from(range);
+        if start == end {
+            return T::id();
+        }
+        self.prod_range_inner(start, end)
+    }
+    fn prod_range_inner(&mut self, start: usize, end: usize) -> T::Item {
+        if let Self::Leaf { val } = self {
+            return val.clone();
+        }
+        if start + self.len() == end {
+            return self.prod();
+        }
+        self.push_down();
+        if let Self::Node { left, right, .. } = self {
+            let mid = left.len();
+            if end <= mid {
+                left.prod_range_inner(start, end)
+            } else if mid <= start {
+                right.prod_range_inner(start - mid, end - mid)
+            } else {
+                T::prod(
+                    &left.prod_range_inner(start, mid),
+                    &right.prod_range_inner(0, end - mid),
+                )
+            }
+        } else {
+            unreachable!()
+        }
+    }
+    /// 添字範囲 `range` の要素全てに `f` を適用する O(log n)
+    pub fn apply_range(&mut self, range: impl RangeBounds<usize>, f: &T::Act) {
+        let Range { start, end } = self.range_from(range);
+        if start == end {
+            return;
+        }
+        self.apply_range_inner(start, end, f);
+    }
+    fn apply_range_inner(&mut self, start: usize, end: usize, f: &T::Act) {
+        if start + self.len() == end {
+            self.apply_all(f);
+            return;
+        }
+        self.push_down();
+        if let Self::Node {
+            left, right, prod, ..
+        } = self
+        {
+            let mid = left.len();
+            if end <= mid {
+                left.apply_range_inner(start, end, f);
+            } else if mid <= start {
+                right.apply_range_inner(start - mid, end - mid, f);
+            } else {
+                left.apply_range_inner(start, mid, f);
+                right.apply_range_inner(0, end - mid, f);
+            }
+            *prod = T::prod(left.prod_ref(), right.prod_ref());
+        }
+    }
+    /// `pred(self.prod_range(start..end))` なる最大の `end`
+    /// `pred(T::id())` が要請される
+    pub fn max_end<P>(&mut self, start: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(start <= self.len(), "index out: {}/{}", start, self.len());
+        if start == self.len() {
+            return start;
+        }
+        let mut acc = T::id();
+        self.max_end_inner(start, &mut pred, &mut acc)
+    }
+    fn max_end_inner<P>(&mut self, start: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if start == 0 {
+            let merged = T::prod(acc, self.prod_ref());
+            if pred(&merged) {
+                *acc = merged;
+                return self.len();
+            }
+        }
+        if let Self::Leaf { .. } = self {
+            return 0;
+        }
+        self.push_down();
+        if let Self::Node { left, right, .. } = self {
+            let mid = left.len();
+            if mid <= start {
+                return mid + right.max_end_inner(start - mid, pred, acc);
+            }
+            let res_l = left.max_end_inner(start, pred, acc);
+            if res_l != mid {
+                res_l
+            } else {
+                mid + right.max_end_inner(0, pred, acc)
+            }
+        } else {
+            unreachable!()
+        }
+    }
+    /// `pred(self.prod_range(start..end))` なる最小の `start`
+    /// `pred(T::id())` が要請される
+    pub fn min_start<P>(&mut self, end: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        if end == 0 {
+            return 0;
+        }
+        let mut acc = T::id();
+        self.min_start_inner(end, &mut pred, &mut acc)
+    }
+    fn min_start_inner<P>(&mut self, end: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if end == self.len() {
+            let merged = T::prod(self.prod_ref(), acc);
+            if pred(&merged) {
+                *acc = merged;
+                return 0;
+            }
+        }
+        if let Self::Leaf { .. } = self {
+            return 1;
+        }
+        self.push_down();
+        if let Self::Node { left, right, .. } = self {
+            let mid = left.len();
+            if end <= mid {
+                return left.min_start_inner(end, pred, acc);
+            }
+            let res_right = right.min_start_inner(end - mid, pred, acc);
+            if res_right != 0 {
+                res_right
+            } else {
+                left.min_start_inner(mid, pred, acc)
+            }
+        } else {
+            unreachable!()
+        }
+    }
+    fn range_from(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        use Bound::*;
+        let start = match range.start_bound() {
+            Included(&a) => a,
+            Excluded(&a) => a + 1,
+            Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Excluded(&a) => a,
+            Included(&a) => a + 1,
+            Unbounded => self.len(),
+        };
+        assert!(start <= end, "invalid range: {}..{}", start, end);
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        Range { start, end }
+    }
+}
+
+impl<T: LazySegTreeType> From<&[T::Item]> for LazySegTree<T> {
+    fn from(slice: &[T::Item]) -> Self {
+        Self::from_slice(slice)
+    }
+}
+
+impl<T: LazySegTreeType> FromIterator<T::Item> for LazySegTree<T> {
+    fn from_iter<I: IntoIterator<Item = T::Item>>(iter: I) -> Self {
+        Self::from(&iter.into_iter().collect::<Vec<_>>()[..])
+    }
+}
+
+/// 更新の度に新しいバージョンを作る永続セグ木
+///
+/// `left`/`right` が `Rc` なので、更新されていない部分木は旧バージョンと共有される
+pub enum PersistentSegTree<T: SegTreeType> {
+    Leaf {
+        val: T::Item,
+    },
+    Node {
+        len: usize,
+        prod: T::Item,
+        left: Rc<Self>,
+        right: Rc<Self>,
+    },
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl<T: SegTreeType> PersistentSegTree<T> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Node { len, .. } => *len,
+        }
+    }
+    pub fn prod_ref(&self) -> &T::Item {
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { prod, .. } => prod,
+        }
+    }
+    pub fn prod(&self) -> T::Item {
+        self.prod_ref().clone()
+    }
+    /// `T::id()` が `n` 個
+    pub fn new(n: usize) -> Self {
+        assert_ne!(n, 0, "empty segtree does not exsist");
+        if n == 1 {
+            Self::Leaf { val: T::id() }
+        } else {
+            Self::Node {
+                len: n,
+                prod: T::id(),
+                left: Rc::new(Self::new(n / 2)),
+                right: Rc::new(Self::new(n - n / 2)),
+            }
+        }
+    }
+    /// スライスから生成
+    fn from_slice(slice: &[T::Item]) -> Self {
+        assert!(!slice.is_empty(), "empty segtree does not exist");
+        if slice.len() == 1 {
+            Self::Leaf {
+                val: slice[0].clone(),
+            }
+        } else {
+            let mid = slice.len() / 2;
+            let left = Self::from_slice(&slice[..mid]);
+            let right = Self::from_slice(&slice[mid..]);
+            Self::Node {
+                len: slice.len(),
+                prod: T::prod(left.prod_ref(), right.prod_ref()),
+                left: Rc::new(left),
+                right: Rc::new(right),
+            }
+        }
+    }
+    /// `i` 番目を得る O(log n)
+    pub fn get(&self, i: usize) -> &T::Item {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if i < mid {
+                    left.get(i)
+                } else {
+                    right.get(i - mid)
+                }
+            }
+        }
+    }
+    /// `i` 番目を `v` にした新しいバージョンを O(log n) で作る
+    ///
+    /// 更新されなかった部分木は `self` と共有される
+    pub fn set(&self, i: usize, v: T::Item) -> Self {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        match self {
+            Self::Leaf { .. } => Self::Leaf { val: v },
+            Self::Node {
+                len, left, right, ..
+            } => {
+                let mid = left.len();
+                let (left, right) = if i < mid {
+                    (Rc::new(left.set(i, v)), Rc::clone(right))
+                } else {
+                    (Rc::clone(left), Rc::new(right.set(i - mid, v)))
+                };
+                Self::Node {
+                    len: *len,
+                    prod: T::prod(left.prod_ref(), right.prod_ref()),
+                    left,
+                    right,
+                }
+            }
+        }
+    }
+    /// 添字範囲 `range` の要素の積 O(log n)
+    pub fn prod_range(&self, range: impl RangeBounds<usize>) -> T::Item {
+        let Range { start, end } = self.range_from(range);
+        if start == end {
+            return T::id();
+        }
+        self.prod_range_inner(start, end)
+    }
+    fn prod_range_inner(&self, start: usize, end: usize) -> T::Item {
+        match self {
+            Self::Leaf { val } => val.clone(),
+            Self::Node {
+                len,
+                prod,
+                left,
+                right,
+            } => {
+                if start + len == end {
+                    return prod.clone();
+                }
+                let mid = left.len();
+                if end <= mid {
+                    left.prod_range_inner(start, end)
+                } else if mid <= start {
+                    right.prod_range_inner(start - mid, end - mid)
+                } else {
+                    T::prod(
+                        &left.prod_range_inner(start, mid),
+                        &right.prod_range_inner(0, end - mid),
+                    )
+                }
+            }
+        }
+    }
+    /// `pred(self.prod_range(start..end))` なる最大の `end`
+    /// `pred(T::id())` が要請される
+    pub fn max_end<P>(&self, start: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(start <= self.len(), "index out: {}/{}", start, self.len());
+        if start == self.len() {
+            return start;
+        }
+        let mut acc = T::id();
+        self.max_end_inner(start, &mut pred, &mut acc)
+    }
+    fn max_end_inner<P>(&self, start: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if start == 0 {
+            let merged = T::prod(acc, self.prod_ref());
+            if pred(&merged) {
+                *acc = merged;
+                return self.len();
+            }
+        }
+        match self {
+            Self::Leaf { .. } => 0,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if mid <= start {
+                    return mid + right.max_end_inner(start - mid, pred, acc);
+                }
+                let res_l = left.max_end_inner(start, pred, acc);
+                if res_l != mid {
+                    res_l
+                } else {
+                    mid + right.max_end_inner(0, pred, acc)
+                }
+            }
+        }
+    }
+    /// `pred(self.prod_range(start..end))` なる最小の `start`
+    /// `pred(T::id())` が要請される
+    pub fn min_start<P>(&self, end: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        if end == 0 {
+            return 0;
+        }
+        let mut acc = T::id();
+        self.min_start_inner(end, &mut pred, &mut acc)
+    }
+    fn min_start_inner<P>(&self, end: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if end == self.len() {
+            let merged = T::prod(self.prod_ref(), acc);
+            if pred(&merged) {
+                *acc = merged;
+                return 0;
+            }
+        }
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if end <= mid {
+                    return left.min_start_inner(end, pred, acc);
+                }
+                let res_right = right.min_start_inner(end - mid, pred, acc);
+                if res_right != 0 {
+                    res_right
+                } else {
+                    left.min_start_inner(mid, pred, acc)
+                }
+            }
+        }
+    }
+    fn range_from(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        use Bound::*;
+        let start = match range.start_bound() {
+            Included(&a) => a,
+            Excluded(&a) => a + 1,
+            Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Excluded(&a) => a,
+            Included(&a) => a + 1,
+            Unbounded => self.len(),
+        };
+        assert!(start <= end, "invalid range: {}..{}", start, end);
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        Range { start, end }
+    }
+}
+
+impl<T: SegTreeType> From<&[T::Item]> for PersistentSegTree<T> {
+    fn from(slice: &[T::Item]) -> Self {
+        Self::from_slice(slice)
+    }
+}
+
+impl<T: SegTreeType> FromIterator<T::Item> for PersistentSegTree<T> {
+    fn from_iter<I: IntoIterator<Item = T::Item>>(iter: I) -> Self {
+        Self::from(&iter.into_iter().collect::<Vec<_>>()[..])
+    }
+}
+
+/// 各ノードが逆順の積 `rev_prod` も保持する `SegTree`
+///
+/// HLD の path query のように非可換な `T::prod` を逆向きにも畳み込みたい場合に使う。
+/// `T::prod` が可換なら `rev_prod` は `prod` と常に一致するので、その場合はオーバーヘッドにしかならない
+pub enum RevSegTree<T: SegTreeType> {
+    Leaf {
+        val: T::Item,
+    },
+    Node {
+        len: usize,
+        prod: T::Item,
+        rev_prod: T::Item,
+        left: Box<Self>,
+        right: Box<Self>,
+    },
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl<T: SegTreeType> RevSegTree<T> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Node { len, .. } => *len,
+        }
+    }
+    pub fn prod_ref(&self) -> &T::Item {
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { prod, .. } => prod,
+        }
+    }
+    pub fn prod(&self) -> T::Item {
+        self.prod_ref().clone()
+    }
+    /// 葉の列を右から左へ辿って積んだもの。`self.prod()` の逆順版
+    pub fn rev_prod_ref(&self) -> &T::Item {
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { rev_prod, .. } => rev_prod,
+        }
+    }
+    pub fn rev_prod(&self) -> T::Item {
+        self.rev_prod_ref().clone()
+    }
+    /// `T::id()` が `n` 個
+    pub fn new(n: usize) -> Self {
+        assert_ne!(n, 0, "empty segtree does not exsist");
+        if n == 1 {
+            Self::Leaf { val: T::id() }
+        } else {
+            Self::Node {
+                len: n,
+                prod: T::id(),
+                rev_prod: T::id(),
+                left: Box::new(Self::new(n / 2)),
+                right: Box::new(Self::new(n - n / 2)),
+            }
+        }
+    }
+    /// スライスから生成
+    fn from_slice(slice: &[T::Item]) -> Self {
+        assert!(!slice.is_empty(), "empty segtree does not exist");
+        if slice.len() == 1 {
+            Self::Leaf {
+                val: slice[0].clone(),
+            }
+        } else {
+            let mid = slice.len() / 2;
+            let left = Self::from_slice(&slice[..mid]);
+            let right = Self::from_slice(&slice[mid..]);
+            Self::Node {
+                len: slice.len(),
+                prod: T::prod(left.prod_ref(), right.prod_ref()),
+                rev_prod: T::prod(right.rev_prod_ref(), left.rev_prod_ref()),
+                left: Box::new(left),
+                right: Box::new(right),
+            }
+        }
+    }
+    /// `i` 番目を得る O(log n)
+    pub fn get(&self, i: usize) -> &T::Item {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if i < mid {
+                    left.get(i)
+                } else {
+                    right.get(i - mid)
+                }
+            }
+        }
+    }
+    /// `i` 番目を `v` にする O(log n)
+    pub fn set(&mut self, i: usize, v: T::Item) {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        match self {
+            Self::Leaf { val } => *val = v,
+            Self::Node {
+                left,
+                right,
+                prod,
+                rev_prod,
+                ..
+            } => {
+                let mid = left.len();
+                if i < mid {
+                    left.set(i, v)
+                } else {
+                    right.set(i - mid, v)
+                }
+                *prod = T::prod(left.prod_ref(), right.prod_ref());
+                *rev_prod = T::prod(right.rev_prod_ref(), left.rev_prod_ref());
+            }
+        }
+    }
+    /// 添字範囲 `range` の要素の積 O(log n)
+    pub fn prod_range(&self, range: impl RangeBounds<usize>) -> T::Item {
+        let Range { start, end } = self.range_from(range);
+        if start == end {
+            return T::id();
+        }
+        self.prod_range_inner(start, end)
+    }
+    fn prod_range_inner(&self, start: usize, end: usize) -> T::Item {
+        match self {
+            Self::Leaf { val } => val.clone(),
+            Self::Node {
+                len,
+                prod,
+                left,
+                right,
+                ..
+            } => {
+                if start + len == end {
+                    return prod.clone();
+                }
+                let mid = left.len();
+                if end <= mid {
+                    left.prod_range_inner(start, end)
+                } else if mid <= start {
+                    right.prod_range_inner(start - mid, end - mid)
+                } else {
+                    T::prod(
+                        &left.prod_range_inner(start, mid),
+                        &right.prod_range_inner(0, end - mid),
+                    )
+                }
+            }
+        }
+    }
+    /// 添字範囲 `range` の要素を右から左へ畳み込んだ積 O(log n)
+    ///
+    /// `T::prod` が可換なら `prod_range(range)` と等しい
+    pub fn prod_range_rev(&self, range: impl RangeBounds<usize>) -> T::Item {
+        let Range { start, end } = self.range_from(range);
+        if start == end {
+            return T::id();
+        }
+        self.prod_range_rev_inner(start, end)
+    }
+    fn prod_range_rev_inner(&self, start: usize, end: usize) -> T::Item {
+        match self {
+            Self::Leaf { val } => val.clone(),
+            Self::Node {
+                len,
+                rev_prod,
+                left,
+                right,
+                ..
+            } => {
+                if start + len == end {
+                    return rev_prod.clone();
+                }
+                let mid = left.len();
+                if end <= mid {
+                    left.prod_range_rev_inner(start, end)
+                } else if mid <= start {
+                    right.prod_range_rev_inner(start - mid, end - mid)
+                } else {
+                    T::prod(
+                        &right.prod_range_rev_inner(0, end - mid),
+                        &left.prod_range_rev_inner(start, mid),
+                    )
+                }
+            }
+        }
+    }
+    /// HLD の path query 用の畳み込み
+    ///
+    /// `up` は LCA に向かって昇る側の区間列（`v` に近い順）、`down` は LCA から `u` へ下る側の区間列
+    /// （LCA に近い順）。`up` 側は `prod_range_rev` で向きを合わせてから、`v` に近いものほど左に来るように
+    /// 結合し、`down` 側はそのまま `prod_range` で結合する
+    pub fn prod_path(
+        &self,
+        up: impl IntoIterator<Item = Range<usize>>,
+        down: impl IntoIterator<Item = Range<usize>>,
+    ) -> T::Item {
+        let mut acc = T::id();
+        for Range { start, end } in up {
+            acc = T::prod(&acc, &self.prod_range_rev(start..end));
+        }
+        for Range { start, end } in down {
+            acc = T::prod(&acc, &self.prod_range(start..end));
+        }
+        acc
+    }
+    /// `pred(self.prod_range(start..end))` なる最大の `end`
+    /// `pred(T::id())` が要請される
+    pub fn max_end<P>(&self, start: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(start <= self.len(), "index out: {}/{}", start, self.len());
+        if start == self.len() {
+            return start;
+        }
+        let mut acc = T::id();
+        self.max_end_inner(start, &mut pred, &mut acc)
+    }
+    fn max_end_inner<P>(&self, start: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if start == 0 {
+            let merged = T::prod(acc, self.prod_ref());
+            if pred(&merged) {
+                *acc = merged;
+                return self.len();
+            }
+        }
+        match self {
+            Self::Leaf { .. } => 0,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if mid <= start {
+                    return mid + right.max_end_inner(start - mid, pred, acc);
+                }
+                let res_l = left.max_end_inner(start, pred, acc);
+                if res_l != mid {
+                    res_l
+                } else {
+                    mid + right.max_end_inner(0, pred, acc)
+                }
+            }
+        }
+    }
+    /// `pred(self.prod_range(start..end))` なる最小の `start`
+    /// `pred(T::id())` が要請される
+    pub fn min_start<P>(&self, end: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        if end == 0 {
+            return 0;
+        }
+        let mut acc = T::id();
+        self.min_start_inner(end, &mut pred, &mut acc)
+    }
+    fn min_start_inner<P>(&self, end: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if end == self.len() {
+            let merged = T::prod(self.prod_ref(), acc);
+            if pred(&merged) {
+                *acc = merged;
+                return 0;
+            }
+        }
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if end <= mid {
+                    return left.min_start_inner(end, pred, acc);
+                }
+                let res_right = right.min_start_inner(end - mid, pred, acc);
+                if res_right != 0 {
+                    res_right
+                } else {
+                    left.min_start_inner(mid, pred, acc)
+                }
+            }
+        }
+    }
+    fn range_from(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        use Bound::*;
+        let start = match range.start_bound() {
+            Included(&a) => a,
+            Excluded(&a) => a + 1,
+            Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Excluded(&a) => a,
+            Included(&a) => a + 1,
+            Unbounded => self.len(),
+        };
+        assert!(start <= end, "invalid range: {}..{}", start, end);
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        Range { start, end }
+    }
+}
+
+impl<T: SegTreeType> From<&[T::Item]> for RevSegTree<T> {
+    fn from(slice: &[T::Item]) -> Self {
+        Self::from_slice(slice)
+    }
+}
+
+impl<T: SegTreeType> FromIterator<T::Item> for RevSegTree<T> {
+    fn from_iter<I: IntoIterator<Item = T::Item>>(iter: I) -> Self {
+        Self::from(&iter.into_iter().collect::<Vec<_>>()[..])
+    }
+}
+
+/// 要素の挿入・削除が O(log n) でできる重み平衡な `SegTree`
+pub enum DynamicSegTree<T: SegTreeType> {
+    Leaf {
+        val: T::Item,
+    },
+    Node {
+        len: usize,
+        prod: T::Item,
+        left: Box<Self>,
+        right: Box<Self>,
+    },
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl<T: SegTreeType> DynamicSegTree<T> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Node { len, .. } => *len,
+        }
+    }
+    pub fn prod_ref(&self) -> &T::Item {
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { prod, .. } => prod,
+        }
+    }
+    pub fn prod(&self) -> T::Item {
+        self.prod_ref().clone()
+    }
+    /// `T::id()` が `n` 個
+    pub fn new(n: usize) -> Self {
+        assert_ne!(n, 0, "empty segtree does not exsist");
+        if n == 1 {
+            Self::Leaf { val: T::id() }
+        } else {
+            Self::Node {
+                len: n,
+                prod: T::id(),
+                left: Box::new(Self::new(n / 2)),
+                right: Box::new(Self::new(n - n / 2)),
+            }
+        }
+    }
+    /// スライスから生成
+    fn from_slice(slice: &[T::Item]) -> Self {
+        assert!(!slice.is_empty(), "empty segtree does not exist");
+        if slice.len() == 1 {
+            Self::Leaf {
+                val: slice[0].clone(),
+            }
+        } else {
+            let mid = slice.len() / 2;
+            let left = Self::from_slice(&slice[..mid]);
+            let right = Self::from_slice(&slice[mid..]);
+            Self::Node {
+                len: slice.len(),
+                prod: T::prod(left.prod_ref(), right.prod_ref()),
+                left: Box::new(left),
+                right: Box::new(right),
+            }
+        }
+    }
+    /// `i` 番目を得る O(log n)
+    pub fn get(&self, i: usize) -> &T::Item {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        match self {
+            Self::Leaf { val } => val,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if i < mid {
+                    left.get(i)
+                } else {
+                    right.get(i - mid)
+                }
+            }
+        }
+    }
+    /// `self` を `T::id()` の葉と取り替え、元の中身を取り出す
+    fn take(&mut self) -> Self {
+        std::mem::replace(self, Self::Leaf { val: T::id() })
+    }
+    /// 自身の葉を左から順に `out` に積む
+    fn collect_leaves(&self, out: &mut Vec<T::Item>) {
+        match self {
+            Self::Leaf { val } => out.push(val.clone()),
+            Self::Node { left, right, .. } => {
+                left.collect_leaves(out);
+                right.collect_leaves(out);
+            }
+        }
+    }
+    /// 子の大きさの偏りが激しければ葉から組み直す
+    fn rebalance(&mut self) {
+        if let Self::Node { left, right, .. } = self {
+            let (l, r) = (left.len(), right.len());
+            if l > 2 * r + 1 || r > 2 * l + 1 {
+                let mut leaves = Vec::with_capacity(l + r);
+                self.collect_leaves(&mut leaves);
+                *self = Self::from_slice(&leaves);
+            }
+        }
+    }
+    /// `i` 番目に `v` を挿入する O(log n) amortized
+    pub fn insert(&mut self, i: usize, v: T::Item) {
+        assert!(i <= self.len(), "index out: {}/{}", i, self.len());
+        match self.take() {
+            Self::Leaf { val } => {
+                let (left, right) = if i == 0 { (v, val) } else { (val, v) };
+                *self = Self::Node {
+                    len: 2,
+                    prod: T::prod(&left, &right),
+                    left: Box::new(Self::Leaf { val: left }),
+                    right: Box::new(Self::Leaf { val: right }),
+                };
+            }
+            Self::Node {
+                len,
+                mut left,
+                mut right,
+                ..
+            } => {
+                let mid = left.len();
+                if i <= mid {
+                    left.insert(i, v);
+                } else {
+                    right.insert(i - mid, v);
+                }
+                *self = Self::Node {
+                    len: len + 1,
+                    prod: T::prod(left.prod_ref(), right.prod_ref()),
+                    left,
+                    right,
+                };
+                self.rebalance();
+            }
+        }
+    }
+    /// `i` 番目を取り除いて返す O(log n) amortized
+    pub fn delete(&mut self, i: usize) -> T::Item {
+        assert!(i < self.len(), "index out: {}/{}", i, self.len());
+        match self.take() {
+            Self::Leaf { .. } => panic!("cannot delete the only remaining element"),
+            Self::Node {
+                len,
+                mut left,
+                mut right,
+                ..
+            } => {
+                let mid = left.len();
+                if i < mid {
+                    if mid == 1 {
+                        let val = match *left {
+                            Self::Leaf { val } => val,
+                            Self::Node { .. } => unreachable!(),
+                        };
+                        *self = *right;
+                        return val;
+                    }
+                    let val = left.delete(i);
+                    *self = Self::Node {
+                        len: len - 1,
+                        prod: T::prod(left.prod_ref(), right.prod_ref()),
+                        left,
+                        right,
+                    };
+                    self.rebalance();
+                    val
+                } else {
+                    let rlen = right.len();
+                    if rlen == 1 {
+                        let val = match *right {
+                            Self::Leaf { val } => val,
+                            Self::Node { .. } => unreachable!(),
+                        };
+                        *self = *left;
+                        return val;
+                    }
+                    let val = right.delete(i - mid);
+                    *self = Self::Node {
+                        len: len - 1,
+                        prod: T::prod(left.prod_ref(), right.prod_ref()),
+                        left,
+                        right,
+                    };
+                    self.rebalance();
+                    val
+                }
+            }
+        }
+    }
+    /// 添字範囲 `range` の要素の積 O(log n)
+    pub fn prod_range(&self, range: impl RangeBounds<usize>) -> T::Item {
+        let Range { start, end } = self.range_from(range);
+        if start == end {
+            return T::id();
+        }
+        self.prod_range_inner(start, end)
+    }
+    fn prod_range_inner(&self, start: usize, end: usize) -> T::Item {
+        match self {
+            Self::Leaf { val } => val.clone(),
+            Self::Node {
+                len,
+                prod,
+                left,
+                right,
+            } => {
+                if start + len == end {
+                    return prod.clone();
+                }
+                let mid = left.len();
+                if end <= mid {
+                    left.prod_range_inner(start, end)
+                } else if mid <= start {
+                    right.prod_range_inner(start - mid, end - mid)
+                } else {
+                    T::prod(
+                        &left.prod_range_inner(start, mid),
+                        &right.prod_range_inner(0, end - mid),
+                    )
+                }
+            }
+        }
+    }
+    /// `pred(self.prod_range(start..end))` なる最大の `end`
+    /// `pred(T::id())` が要請される
+    pub fn max_end<P>(&self, start: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(start <= self.len(), "index out: {}/{}", start, self.len());
+        if start == self.len() {
+            return start;
+        }
+        let mut acc = T::id();
+        self.max_end_inner(start, &mut pred, &mut acc)
+    }
+    fn max_end_inner<P>(&self, start: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if start == 0 {
+            let merged = T::prod(acc, self.prod_ref());
+            if pred(&merged) {
+                *acc = merged;
+                return self.len();
+            }
+        }
+        match self {
+            Self::Leaf { .. } => 0,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if mid <= start {
+                    return mid + right.max_end_inner(start - mid, pred, acc);
+                }
+                let res_l = left.max_end_inner(start, pred, acc);
+                if res_l != mid {
+                    res_l
+                } else {
+                    mid + right.max_end_inner(0, pred, acc)
+                }
+            }
+        }
+    }
+    /// `pred(self.prod_range(start..end))` なる最小の `start`
+    /// `pred(T::id())` が要請される
+    pub fn min_start<P>(&self, end: usize, mut pred: P) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        if end == 0 {
+            return 0;
+        }
+        let mut acc = T::id();
+        self.min_start_inner(end, &mut pred, &mut acc)
+    }
+    fn min_start_inner<P>(&self, end: usize, pred: &mut P, acc: &mut T::Item) -> usize
+    where
+        P: FnMut(&T::Item) -> bool,
+    {
+        if end == self.len() {
+            let merged = T::prod(self.prod_ref(), acc);
+            if pred(&merged) {
+                *acc = merged;
+                return 0;
+            }
+        }
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Node { left, right, .. } => {
+                let mid = left.len();
+                if end <= mid {
+                    return left.min_start_inner(end, pred, acc);
+                }
+                let res_right = right.min_start_inner(end - mid, pred, acc);
+                if res_right != 0 {
+                    res_right
+                } else {
+                    left.min_start_inner(mid, pred, acc)
+                }
+            }
+        }
+    }
+    fn range_from(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        use Bound::*;
+        let start = match range.start_bound() {
+            Included(&a) => a,
+            Excluded(&a) => a + 1,
+            Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Excluded(&a) => a,
+            Included(&a) => a + 1,
+            Unbounded => self.len(),
+        };
+        assert!(start <= end, "invalid range: {}..{}", start, end);
+        assert!(end <= self.len(), "index out: {}/{}", end, self.len());
+        Range { start, end }
+    }
+}
+
+impl<T: SegTreeType> From<&[T::Item]> for DynamicSegTree<T> {
+    fn from(slice: &[T::Item]) -> Self {
+        Self::from_slice(slice)
+    }
+}
+
+impl<T: SegTreeType> FromIterator<T::Item> for DynamicSegTree<T> {
+    fn from_iter<I: IntoIterator<Item = T::Item>>(iter: I) -> Self {
+        Self::from(&iter.into_iter().collect::<Vec<_>>()[..])
+    }
+}